@@ -11,14 +11,17 @@ use glium::{
 };
 
 use mujoco_rs_sys::{
-    mjr_makeContext, mjr_overlay, mjr_readPixels, mjtCatBit, mjtFont, mjtGridPos,
-    mjvCamera, mjvOption, mjvPerturb, mjvScene, mjv_defaultFreeCamera,
-    mjv_defaultOption, mjv_defaultPerturb, mjv_makeScene, mjv_updateScene,
+    mjr_freeContext, mjr_makeContext, mjr_overlay, mjr_readPixels, mjr_setBuffer, mjtCatBit,
+    mjtFont, mjtFramebuffer, mjtGridPos, mjtMouse, mjtPertBit, mjtVisFlag, mjvCamera, mjvOption,
+    mjvPerturb, mjvScene, mjv_applyPerturbForce, mjv_applyPerturbPose, mjv_defaultFreeCamera,
+    mjv_defaultOption, mjv_defaultPerturb, mjv_freeScene, mjv_initPerturb, mjv_makeScene,
+    mjv_moveCamera, mjv_movePerturb, mjv_select, mjv_updateScene,
     render::{mjrContext, mjrRect, mjr_render},
 };
 use std::{
     num::NonZeroU32,
-    sync::{Arc, Mutex},
+    path::Path,
+    sync::{mpsc, Arc, Mutex},
     thread,
     time::{Duration, Instant},
 };
@@ -34,7 +37,9 @@ use glium::{
     },
     texture::RawImage2d,
     winit::{
-        event::WindowEvent, keyboard::NamedKey, raw_window_handle::HasWindowHandle,
+        event::{MouseButton, MouseScrollDelta, WindowEvent},
+        keyboard::NamedKey,
+        raw_window_handle::HasWindowHandle,
     },
     Surface,
 };
@@ -43,6 +48,125 @@ use glutin_winit::DisplayBuilder;
 
 type CtrlFun = Box<dyn FnMut(&mujoco_rust::Simulation) -> Vec<f64> + Send + 'static>;
 type RenderFun = Box<dyn FnMut()>;
+type FrameFun = Box<dyn FnMut(Vec<u8>, u32, u32) + Send + 'static>;
+type GamepadMapping = Box<dyn Fn(&GamepadState) -> Vec<f64> + Send + 'static>;
+
+/// Deadzone-filtered stick readings and zero-clamped trigger readings for the
+/// first connected gamepad,
+/// handed to a [`GamepadMapping`] to produce a control vector.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GamepadState {
+    pub left_stick: [f64; 2],
+    pub right_stick: [f64; 2],
+    pub left_trigger: f64,
+    pub right_trigger: f64,
+}
+
+const GAMEPAD_DEADZONE: f32 = 0.15;
+
+/// Default cap applied by `with_offscreen_rendering` so a caller who forgets
+/// `with_max_frames`/`with_duration` still gets a run that terminates
+/// instead of capturing frames forever.
+const DEFAULT_OFFSCREEN_DURATION_SECS: f64 = 10.0;
+
+fn apply_deadzone(value: f32, deadzone: f32) -> f64 {
+    if value.abs() < deadzone {
+        0.0
+    } else {
+        value as f64
+    }
+}
+
+fn read_gamepad_state(gamepad: gilrs::Gamepad) -> GamepadState {
+    use gilrs::Axis;
+    GamepadState {
+        left_stick: [
+            apply_deadzone(gamepad.value(Axis::LeftStickX), GAMEPAD_DEADZONE),
+            apply_deadzone(gamepad.value(Axis::LeftStickY), GAMEPAD_DEADZONE),
+        ],
+        right_stick: [
+            apply_deadzone(gamepad.value(Axis::RightStickX), GAMEPAD_DEADZONE),
+            apply_deadzone(gamepad.value(Axis::RightStickY), GAMEPAD_DEADZONE),
+        ],
+        left_trigger: gamepad.value(Axis::LeftZ).max(0.0) as f64,
+        right_trigger: gamepad.value(Axis::RightZ).max(0.0) as f64,
+    }
+}
+
+/// Left/right stick axes drive the first four actuators and the triggers the
+/// next two, so plugging in a controller immediately teleoperates an
+/// actuated model with no mapping code required.
+fn default_gamepad_mapping(nu: usize) -> GamepadMapping {
+    Box::new(move |gamepad: &GamepadState| {
+        let axes = [
+            gamepad.left_stick[0],
+            gamepad.left_stick[1],
+            gamepad.right_stick[0],
+            gamepad.right_stick[1],
+            gamepad.left_trigger,
+            gamepad.right_trigger,
+        ];
+        let mut control = vec![0.0; nu];
+        for (val, axis) in control.iter_mut().zip(axes) {
+            *val = axis;
+        }
+        control
+    })
+}
+
+/// Where captured offscreen frames go: a user callback, or a numbered image
+/// sequence written to disk.
+enum FrameOutput {
+    Callback(FrameFun),
+    Sequence {
+        dir: String,
+        prefix: String,
+        format: ImageFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+fn write_frame(path: &str, rgb: &[u8], width: u32, height: u32, format: ImageFormat) {
+    match format {
+        ImageFormat::Png => {
+            image::save_buffer(path, rgb, width, height, image::ColorType::Rgb8)
+                .expect("Failed to write PNG frame");
+        }
+        ImageFormat::Ppm => {
+            use std::io::Write;
+            let mut file = std::fs::File::create(path).expect("Failed to create PPM frame");
+            write!(file, "P6\n{width} {height}\n255\n").unwrap();
+            file.write_all(rgb).unwrap();
+        }
+    }
+}
+
+/// Whether `run_offscreen` should capture a frame this step: true once
+/// simulated time has advanced to (within half a timestep of) the next
+/// `1 / fps` boundary.
+fn due_for_next_frame(sim_time: f64, timestep: f64, next_frame_time: f64) -> bool {
+    sim_time + timestep / 2.0 >= next_frame_time
+}
+
+/// Converts a capture duration into a frame count at the given fps, rounding
+/// up so the requested duration is never cut short by truncation.
+fn offscreen_frame_cap(seconds: f64, fps: f64) -> u64 {
+    (seconds * fps).ceil() as u64
+}
 
 // The simulation itself and model are handled by mj-rust's Simulation object
 struct RenderState {
@@ -71,6 +195,80 @@ struct Rendering {
     window: Window,
     display: Display<WindowSurface>,
     render_cb: Option<RenderFun>,
+    egui_ctx: egui::Context,
+    egui_winit_state: egui_winit::State,
+    egui_painter: egui_glium::Painter,
+    mouse: MouseState,
+    // Pixel buffers and the texture they're uploaded into are reallocated
+    // only when `window.inner_size()` actually changes, instead of every
+    // frame.
+    frame_buffers: FrameBuffers,
+    // Wall-clock target for the next `request_redraw`, used to pace the
+    // render loop to `target_frame_interval` instead of busy-spinning.
+    target_frame_interval: Duration,
+    next_redraw: Instant,
+}
+
+#[derive(Default)]
+struct FrameBuffers {
+    size: (u32, u32),
+    rgb: Vec<u8>,
+    depth: Vec<f32>,
+    texture: Option<glium::Texture2d>,
+}
+
+/// A headless counterpart to `Rendering`: an offscreen GL context + MuJoCo
+/// scene/context pair driven by a deterministic physics/capture loop instead
+/// of a windowing event loop.
+struct OffscreenRendering {
+    width: u32,
+    height: u32,
+    fps: f64,
+    // Capped by `with_max_frames` / `with_duration` so `run_offscreen`
+    // terminates instead of capturing forever; `None` (set by
+    // `without_frame_limit`) means no cap.
+    max_frames: Option<u64>,
+    state: RenderState,
+    // Kept alive for the lifetime of the GL context; never shown, polled, or
+    // drawn to directly (MuJoCo renders into the bound offscreen buffer).
+    _window: Window,
+    _display: Display<WindowSurface>,
+    output: FrameOutput,
+}
+
+/// Tracks button state and position needed to turn mouse drags into camera
+/// moves / body perturbation, mirroring MuJoCo's own `simulate` viewer.
+#[derive(Default)]
+struct MouseState {
+    left_down: bool,
+    right_down: bool,
+    middle_down: bool,
+    last_pos: Option<(f64, f64)>,
+    last_left_press: Option<Instant>,
+}
+
+impl MouseState {
+    fn action(&self) -> Option<mjtMouse> {
+        if self.right_down {
+            Some(mjtMouse::MOVE_H)
+        } else if self.middle_down {
+            Some(mjtMouse::ZOOM)
+        } else if self.left_down {
+            Some(mjtMouse::ROTATE_V)
+        } else {
+            None
+        }
+    }
+}
+
+/// Draws a checkbox bound to a single entry of `opt.flags`, the per-flag array
+/// MuJoCo uses to toggle scene visualization (contacts, joints, transparency, ...).
+fn vis_flag_checkbox(ui: &mut egui::Ui, label: &str, opt: &mut mjvOption, flag: mjtVisFlag) {
+    let idx = flag as usize;
+    let mut enabled = opt.flags[idx] != 0;
+    if ui.checkbox(&mut enabled, label).changed() {
+        opt.flags[idx] = enabled as u8;
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -84,33 +282,70 @@ enum PhysicsRunningState {
 struct PhysicsState {
     running_state: PhysicsRunningState,
     frame_rate: f32,
+    step_once: bool,
+    reset_requested: bool,
+}
+
+/// Drains pending gilrs events (to keep its connection state fresh) and, if
+/// a gamepad is connected, runs the user's mapping over its current axes.
+fn poll_gamepad(
+    gilrs: Option<&mut gilrs::Gilrs>,
+    mapping: Option<&GamepadMapping>,
+) -> Option<Vec<f64>> {
+    let gilrs = gilrs?;
+    let mapping = mapping?;
+    while gilrs.next_event().is_some() {}
+    let (_, gamepad) = gilrs.gamepads().next()?;
+    Some(mapping(&read_gamepad_state(gamepad)))
 }
 
 fn loop_physics_threaded(
     sim: Arc<Mutex<mujoco_rust::Simulation>>,
     state: Arc<Mutex<PhysicsState>>,
+    gui_ctrl: Arc<Mutex<Option<Vec<f64>>>>,
     mut ctrl_cb: Option<CtrlFun>,
+    gamepad_mapping: Option<GamepadMapping>,
 ) {
     let mut last_updated = Instant::now();
     // We should have taken one step, so we know the timestep
     let timestep = sim.lock().unwrap().state.time();
+    let mut gilrs = gamepad_mapping
+        .is_some()
+        .then(|| gilrs::Gilrs::new().expect("Failed to initialize gamepad subsystem"));
     let mut step_sim = || {
         let locked_sim = sim.lock().unwrap();
-        // Apply control law here
-        if let Some(fun) = ctrl_cb.as_mut() {
+        // The GUI control panel takes priority, but only once a slider has
+        // actually been touched (see draw_gui) — otherwise merely showing a
+        // window would silently shadow the gamepad mapping below with
+        // zero-initialized sliders.
+        if let Some(control) = gui_ctrl.lock().unwrap().as_ref() {
+            locked_sim.control(control);
+        } else if let Some(control) = poll_gamepad(gilrs.as_mut(), gamepad_mapping.as_ref()) {
+            locked_sim.control(&control);
+        } else if let Some(fun) = ctrl_cb.as_mut() {
             let control = fun(&locked_sim);
             locked_sim.control(&control);
         }
         locked_sim.step();
     };
     loop {
-        let current_state: PhysicsState;
+        let mut current_state: PhysicsState;
         {
-            let locked = state.lock().unwrap();
-            current_state = (*locked).clone()
+            let mut locked = state.lock().unwrap();
+            if locked.reset_requested {
+                sim.lock().unwrap().reset();
+                locked.reset_requested = false;
+            }
+            current_state = (*locked).clone();
+            if current_state.running_state == PhysicsRunningState::Paused && locked.step_once {
+                locked.step_once = false;
+            }
         }
         match current_state.running_state {
             PhysicsRunningState::Paused => {
+                if current_state.step_once {
+                    step_sim();
+                }
                 last_updated = Instant::now();
                 thread::sleep(Duration::from_millis(1));
             }
@@ -136,15 +371,26 @@ pub struct MujocoApp {
     rendering: Option<Rendering>,
     sim: Arc<Mutex<mujoco_rust::Simulation>>,
     physics_state: Arc<Mutex<PhysicsState>>,
+    // Control vector driven by the egui sliders; takes priority over `ctrl_cb`
+    // whenever the control panel has been touched.
+    gui_ctrl: Arc<Mutex<Option<Vec<f64>>>>,
     frame_rate_limited: bool,
     last_render: Instant,
+    // Hot-reload support: the XML path we're watching (if any), a flag set
+    // by the watcher thread, and the last reload failure to surface to the user.
+    xml_path: Option<String>,
+    pending_reload: Arc<Mutex<bool>>,
+    reload_error: Arc<Mutex<Option<String>>>,
+    offscreen: Option<OffscreenRendering>,
+    gamepad_mapping: Option<GamepadMapping>,
 }
 
 impl MujocoApp {
     pub fn run_app(mut self) {
-        if self.rendering.is_some() {
-            let event_loop =
-                self.rendering.as_mut().unwrap().event_loop.take().unwrap();
+        if self.offscreen.is_some() {
+            self.run_offscreen();
+        } else if self.rendering.is_some() {
+            let event_loop = self.rendering.as_mut().unwrap().event_loop.take().unwrap();
             self.launch_physics_thread();
             event_loop.run_app(&mut self).unwrap();
         }
@@ -158,16 +404,26 @@ impl MujocoApp {
     fn launch_physics_thread(&mut self) {
         let sim_clone = self.sim.clone();
         let physics_state_clone = self.physics_state.clone();
+        let gui_ctrl_clone = self.gui_ctrl.clone();
         let ctrl_cb = self.ctrl_cb.take();
+        let gamepad_mapping = self.gamepad_mapping.take();
         thread::spawn(move || {
-            loop_physics_threaded(sim_clone, physics_state_clone, ctrl_cb);
+            loop_physics_threaded(
+                sim_clone,
+                physics_state_clone,
+                gui_ctrl_clone,
+                ctrl_cb,
+                gamepad_mapping,
+            );
         });
     }
 
     fn loop_physics(&mut self) {
         loop {
             let sim = self.sim.lock().unwrap();
-            if let Some(fun) = self.ctrl_cb.as_mut() {
+            if let Some(control) = self.gui_ctrl.lock().unwrap().as_ref() {
+                sim.control(control);
+            } else if let Some(fun) = self.ctrl_cb.as_mut() {
                 let control = fun(&sim);
                 sim.control(&control);
             }
@@ -175,14 +431,101 @@ impl MujocoApp {
         }
     }
 
+    /// Deterministic headless loop: steps physics at the model timestep and
+    /// captures one frame every `1 / fps` of simulated time, so the output
+    /// doesn't depend on how fast this machine happens to run. `max_frames`
+    /// defaults to `DEFAULT_OFFSCREEN_DURATION_SECS` worth of frames (see
+    /// `with_offscreen_rendering`) and stops the loop once reached; `None`
+    /// (via `without_frame_limit`) runs until the process is killed.
+    fn run_offscreen(&mut self) {
+        let mut offscreen = self.offscreen.take().unwrap();
+        let timestep = self.sim.lock().unwrap().state.time();
+        let frame_interval = 1.0 / offscreen.fps;
+        let mut next_frame_time = 0.0;
+        let mut frame_index: u64 = 0;
+
+        loop {
+            {
+                let sim = self.sim.lock().unwrap();
+                if let Some(fun) = self.ctrl_cb.as_mut() {
+                    let control = fun(&sim);
+                    sim.control(&control);
+                }
+                sim.step();
+            }
+
+            let sim_time = self.sim.lock().unwrap().state.time();
+            if due_for_next_frame(sim_time, timestep, next_frame_time) {
+                self.capture_offscreen_frame(&mut offscreen, frame_index);
+                frame_index += 1;
+                next_frame_time += frame_interval;
+                if offscreen.max_frames.is_some_and(|max| frame_index >= max) {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn capture_offscreen_frame(&mut self, offscreen: &mut OffscreenRendering, frame_index: u64) {
+        let viewport = mjrRect {
+            left: 0,
+            bottom: 0,
+            width: offscreen.width as i32,
+            height: offscreen.height as i32,
+        };
+        let num_pixels = (offscreen.width * offscreen.height) as usize;
+        let mut rgb = vec![0u8; num_pixels * 3];
+        let mut depth = vec![0.0f32; num_pixels];
+
+        {
+            let locked_sim = self.sim.lock().unwrap();
+            let m = locked_sim.model.ptr();
+            let d = locked_sim.state.ptr();
+            unsafe {
+                mjv_updateScene(
+                    m,
+                    d,
+                    &mut offscreen.state.opt,
+                    &offscreen.state.pert,
+                    &mut offscreen.state.cam,
+                    mjtCatBit::ALL as i32,
+                    &mut offscreen.state.scn,
+                );
+                mjr_render(viewport, &mut offscreen.state.scn, &offscreen.state.con);
+                mjr_readPixels(
+                    rgb.as_mut_ptr(),
+                    depth.as_mut_ptr(),
+                    viewport,
+                    &offscreen.state.con,
+                );
+            }
+        }
+
+        match &mut offscreen.output {
+            FrameOutput::Callback(fun) => fun(rgb, offscreen.width, offscreen.height),
+            FrameOutput::Sequence {
+                dir,
+                prefix,
+                format,
+            } => {
+                let path = format!(
+                    "{dir}/{prefix}{frame_index:06}.{ext}",
+                    ext = format.extension()
+                );
+                write_frame(&path, &rgb, offscreen.width, offscreen.height, *format);
+            }
+        }
+    }
+
     fn render(&mut self) {
+        self.maybe_reload_model();
         if let Some(rendering) = self.rendering.as_mut() {
             let fps = 1. / self.last_render.elapsed().as_secs_f32();
             self.last_render = Instant::now();
             {
                 self.physics_state.lock().unwrap().frame_rate = fps;
             }
-            let target = rendering.display.draw();
+            let mut target = rendering.display.draw();
 
             let window_size = rendering.window.inner_size();
             let viewport = mjrRect {
@@ -192,11 +535,31 @@ impl MujocoApp {
                 height: window_size.height as i32,
             };
 
-            let num_pixels: usize = (window_size.width * window_size.height) as usize;
-
-            let mut rgb = vec![0u8; num_pixels * 3];
-            let mut depth = vec![0.0f32; num_pixels];
+            let buffers = &mut rendering.frame_buffers;
+            if buffers.size != (window_size.width, window_size.height) || buffers.texture.is_none()
+            {
+                buffers.size = (window_size.width, window_size.height);
+                let num_pixels = (window_size.width * window_size.height) as usize;
+                buffers.rgb = vec![0u8; num_pixels * 3];
+                buffers.depth = vec![0.0f32; num_pixels];
+                buffers.texture = Some(
+                    glium::Texture2d::empty(
+                        &rendering.display,
+                        window_size.width,
+                        window_size.height,
+                    )
+                    .unwrap(),
+                );
+            }
+            let rgb = &mut rendering.frame_buffers.rgb;
+            let depth = &mut rendering.frame_buffers.depth;
 
+            // Read before taking `sim` below: the physics thread locks in the
+            // order physics_state -> sim (see loop_physics_threaded's reset
+            // path), so locking sim -> physics_state here as well would let
+            // a held perturb and a concurrent Reset click deadlock.
+            let paused =
+                self.physics_state.lock().unwrap().running_state == PhysicsRunningState::Paused;
             {
                 let locked_sim = self.sim.lock().unwrap();
                 let timestamp = format!("Time = {:.3}", locked_sim.state.time());
@@ -206,6 +569,22 @@ impl MujocoApp {
                 let m = locked_sim.model.ptr();
                 let d = locked_sim.state.ptr();
                 unsafe {
+                    // mj_step never clears xfrc_applied, so last frame's
+                    // perturb force would otherwise keep pushing the body
+                    // forever once the grab is released. Zero it before
+                    // possibly reapplying, mirroring MuJoCo's own `simulate`.
+                    let nbody = locked_sim.model.nbody();
+                    std::slice::from_raw_parts_mut((*d).xfrc_applied, 6 * nbody).fill(0.0);
+
+                    // Apply any active body perturbation before the scene is
+                    // updated, so a grabbed body visibly moves this frame.
+                    if rendering.state.pert.active != 0 {
+                        if paused {
+                            mjv_applyPerturbPose(m, d, &rendering.state.pert, 1);
+                        } else {
+                            mjv_applyPerturbForce(m, d, &rendering.state.pert);
+                        }
+                    }
                     // Update camera / scene etc.
                     mjv_updateScene(
                         m,
@@ -217,11 +596,7 @@ impl MujocoApp {
                         &mut rendering.state.scn,
                     );
                     // Render to a frame buffer
-                    mjr_render(
-                        viewport,
-                        &mut rendering.state.scn,
-                        &rendering.state.con,
-                    );
+                    mjr_render(viewport, &mut rendering.state.scn, &rendering.state.con);
                     // Overlay text
                     mjr_overlay(
                         mjtFont::NORMAL as i32,
@@ -240,10 +615,22 @@ impl MujocoApp {
                     );
                 }
             }
-            let color_image =
-                RawImage2d::from_raw_rgb(rgb, (window_size.width, window_size.height));
-            let color_texture: glium::Texture2d =
-                glium::Texture2d::new(&rendering.display, color_image).unwrap();
+            let color_image = RawImage2d {
+                data: std::borrow::Cow::Borrowed(&rgb[..]),
+                width: window_size.width,
+                height: window_size.height,
+                format: glium::texture::ClientFormat::U8U8U8,
+            };
+            let color_texture = rendering.frame_buffers.texture.as_ref().unwrap();
+            color_texture.write(
+                glium::Rect {
+                    left: 0,
+                    bottom: 0,
+                    width: window_size.width,
+                    height: window_size.height,
+                },
+                color_image,
+            );
             color_texture
                 .as_surface()
                 .fill(&target, glium::uniforms::MagnifySamplerFilter::Linear);
@@ -254,9 +641,360 @@ impl MujocoApp {
                 fun();
             }
 
+            self.draw_gui(&mut target);
+
             target.finish().unwrap();
         }
     }
+
+    /// If the watched XML file has changed, reload the model and rebuild
+    /// the render resources in place. Keeps the old model running (with an
+    /// overlay error) if the new XML fails to parse.
+    fn maybe_reload_model(&mut self) {
+        let mut pending = self.pending_reload.lock().unwrap();
+        if !*pending {
+            return;
+        }
+        *pending = false;
+        drop(pending);
+
+        let Some(xml_path) = self.xml_path.clone() else {
+            return;
+        };
+
+        match mujoco_rust::Model::from_xml(xml_path.clone()) {
+            Ok(model) => {
+                let new_sim = mujoco_rust::Simulation::new(model);
+                new_sim.step();
+
+                *self.sim.lock().unwrap() = new_sim;
+
+                // The reloaded model can have a different nu/body count, so
+                // any state keyed to the old one is now stale: the gui_ctrl
+                // buffer's length may no longer match nu, and a perturb
+                // selection may point at a body that no longer exists.
+                *self.gui_ctrl.lock().unwrap() = None;
+
+                if let Some(rendering) = self.rendering.as_mut() {
+                    rendering.state.pert.select = 0;
+                    rendering.state.pert.active = 0;
+
+                    let state = &mut rendering.state;
+                    let m = self.sim.lock().unwrap().model.ptr();
+                    unsafe {
+                        mjv_freeScene(&mut state.scn);
+                        mjr_freeContext(&mut state.con);
+                        mjv_makeScene(m, &mut state.scn, 1000);
+                        mjr_makeContext(m, &mut state.con, 11);
+                        mjv_defaultFreeCamera(m, &mut state.cam);
+                    }
+                }
+                *self.reload_error.lock().unwrap() = None;
+            }
+            Err(_) => {
+                *self.reload_error.lock().unwrap() = Some(format!(
+                    "Failed to reload '{}', keeping previous model",
+                    xml_path
+                ));
+            }
+        }
+    }
+
+    /// Draws the egui control panel (play/pause/step/reset, visualization
+    /// flag toggles and ctrl sliders) on top of the MuJoCo framebuffer.
+    fn draw_gui(&mut self, target: &mut glium::Frame) {
+        let physics_state = self.physics_state.clone();
+        let gui_ctrl = self.gui_ctrl.clone();
+        let sim = self.sim.clone();
+        let reload_error = self.reload_error.clone();
+        let frame_rate_limited = self.frame_rate_limited;
+        let rendering = self.rendering.as_mut().unwrap();
+
+        let raw_input = rendering
+            .egui_winit_state
+            .take_egui_input(&rendering.window);
+        let egui_output = rendering.egui_ctx.run(raw_input, |ctx| {
+            egui::Window::new("MuJoCo Controls").show(ctx, |ui| {
+                if let Some(err) = reload_error.lock().unwrap().as_ref() {
+                    ui.colored_label(egui::Color32::RED, err);
+                    ui.separator();
+                }
+
+                let mut phys = physics_state.lock().unwrap();
+                ui.horizontal(|ui| {
+                    let label = if phys.running_state == PhysicsRunningState::Paused {
+                        "Play"
+                    } else {
+                        "Pause"
+                    };
+                    if ui.button(label).clicked() {
+                        phys.running_state = match phys.running_state {
+                            PhysicsRunningState::Paused if frame_rate_limited => {
+                                PhysicsRunningState::RateLimited
+                            }
+                            PhysicsRunningState::Paused => PhysicsRunningState::Uncapped,
+                            _ => PhysicsRunningState::Paused,
+                        };
+                    }
+                    let paused = phys.running_state == PhysicsRunningState::Paused;
+                    if ui.add_enabled(paused, egui::Button::new("Step")).clicked() {
+                        phys.step_once = true;
+                    }
+                    if ui.button("Reset").clicked() {
+                        phys.reset_requested = true;
+                    }
+                });
+                ui.label(format!("FPS: {:.1}", phys.frame_rate));
+                drop(phys);
+
+                ui.separator();
+                ui.label("Visualization");
+                vis_flag_checkbox(
+                    ui,
+                    "Contact points",
+                    &mut rendering.state.opt,
+                    mjtVisFlag::CONTACTPOINT,
+                );
+                vis_flag_checkbox(
+                    ui,
+                    "Contact forces",
+                    &mut rendering.state.opt,
+                    mjtVisFlag::CONTACTFORCE,
+                );
+                vis_flag_checkbox(ui, "Joints", &mut rendering.state.opt, mjtVisFlag::JOINT);
+                vis_flag_checkbox(
+                    ui,
+                    "Transparency",
+                    &mut rendering.state.opt,
+                    mjtVisFlag::TRANSPARENT,
+                );
+
+                ui.separator();
+                ui.label("Control");
+                let locked_sim = sim.lock().unwrap();
+                let nu = locked_sim.model.nu();
+                let mut gui_ctrl = gui_ctrl.lock().unwrap();
+                // Sliders are drawn from a scratch buffer (falling back to the
+                // last applied values, or zero) rather than `gui_ctrl` itself,
+                // so merely opening the panel doesn't silently hand control
+                // away from the gamepad/`ctrl_cb` below it in priority.
+                let mut ctrl_vals = gui_ctrl.clone().unwrap_or_else(|| vec![0.0; nu]);
+                let mut touched = false;
+                for (i, val) in ctrl_vals.iter_mut().enumerate() {
+                    if ui
+                        .add(egui::Slider::new(val, -1.0..=1.0).text(format!("ctrl[{i}]")))
+                        .changed()
+                    {
+                        touched = true;
+                    }
+                }
+                if touched {
+                    *gui_ctrl = Some(ctrl_vals);
+                }
+
+                ui.separator();
+                ui.label("qpos");
+                let mut qpos_changed = false;
+                let qpos = locked_sim.state.qpos_mut();
+                for (i, val) in qpos.iter_mut().enumerate() {
+                    if ui
+                        .add(egui::Slider::new(val, -3.14..=3.14).text(format!("qpos[{i}]")))
+                        .changed()
+                    {
+                        qpos_changed = true;
+                    }
+                }
+                // qpos alone doesn't determine geom transforms; without
+                // recomputing them here, a paused drag wouldn't visibly move
+                // anything until the next physics step.
+                if qpos_changed {
+                    locked_sim.forward();
+                }
+            });
+        });
+
+        rendering
+            .egui_winit_state
+            .handle_platform_output(&rendering.window, egui_output.platform_output);
+        let clipped_primitives = rendering
+            .egui_ctx
+            .tessellate(egui_output.shapes, egui_output.pixels_per_point);
+        rendering.egui_painter.paint_and_update_textures(
+            &rendering.display,
+            target,
+            egui_output.pixels_per_point,
+            &clipped_primitives,
+            &egui_output.textures_delta,
+        );
+    }
+
+    fn handle_mouse_input(&mut self, state: ElementState, button: MouseButton) {
+        let Some(rendering) = self.rendering.as_mut() else {
+            return;
+        };
+        let pressed = state == ElementState::Pressed;
+        match button {
+            MouseButton::Left => {
+                rendering.mouse.left_down = pressed;
+                if pressed {
+                    let now = Instant::now();
+                    let double_clicked = rendering
+                        .mouse
+                        .last_left_press
+                        .is_some_and(|t| now.duration_since(t) < Duration::from_millis(300));
+                    rendering.mouse.last_left_press = Some(now);
+                    if double_clicked {
+                        self.select_body_under_cursor();
+                    }
+                } else {
+                    let body = rendering.state.pert.select;
+                    rendering.state.pert.active = 0;
+                    // Stop pushing the released body immediately instead of
+                    // waiting for the next render() to zero
+                    // xfrc_applied.
+                    if body >= 0 {
+                        let locked_sim = self.sim.lock().unwrap();
+                        unsafe {
+                            let d = locked_sim.state.ptr();
+                            std::slice::from_raw_parts_mut(
+                                (*d).xfrc_applied.add(6 * body as usize),
+                                6,
+                            )
+                            .fill(0.0);
+                        }
+                    }
+                }
+            }
+            MouseButton::Right => rendering.mouse.right_down = pressed,
+            MouseButton::Middle => rendering.mouse.middle_down = pressed,
+            _ => {}
+        }
+    }
+
+    fn handle_cursor_moved(&mut self, x: f64, y: f64) {
+        let Some(rendering) = self.rendering.as_mut() else {
+            return;
+        };
+        let last_pos = rendering.mouse.last_pos.replace((x, y));
+        let Some((last_x, last_y)) = last_pos else {
+            return;
+        };
+        let Some(action) = rendering.mouse.action() else {
+            return;
+        };
+        let height = rendering.window.inner_size().height as f64;
+        let dx = (x - last_x) / height;
+        let dy = (y - last_y) / height;
+
+        let locked_sim = self.sim.lock().unwrap();
+        let m = locked_sim.model.ptr();
+        let d = locked_sim.state.ptr();
+        unsafe {
+            if rendering.state.pert.active != 0 {
+                mjv_movePerturb(
+                    m,
+                    d,
+                    action as i32,
+                    dx,
+                    dy,
+                    &rendering.state.scn,
+                    &mut rendering.state.pert,
+                );
+            } else {
+                mjv_moveCamera(
+                    m,
+                    action as i32,
+                    dx,
+                    dy,
+                    &rendering.state.scn,
+                    &mut rendering.state.cam,
+                );
+            }
+        }
+    }
+
+    fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let Some(rendering) = self.rendering.as_mut() else {
+            return;
+        };
+        let scroll_y = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y as f64,
+            MouseScrollDelta::PixelDelta(pos) => pos.y / 100.0,
+        };
+        let locked_sim = self.sim.lock().unwrap();
+        unsafe {
+            mjv_moveCamera(
+                locked_sim.model.ptr(),
+                mjtMouse::ZOOM as i32,
+                0.0,
+                -scroll_y * 0.05,
+                &rendering.state.scn,
+                &mut rendering.state.cam,
+            );
+        }
+    }
+
+    /// Finds the body under the cursor via `mjv_select` and arms the
+    /// perturb struct so the next drag grabs and moves it.
+    fn select_body_under_cursor(&mut self) {
+        let rendering = self.rendering.as_mut().unwrap();
+        let Some((x, y)) = rendering.mouse.last_pos else {
+            return;
+        };
+        let window_size = rendering.window.inner_size();
+        let aspect = window_size.width as f64 / window_size.height as f64;
+        let relx = x / window_size.width as f64;
+        let rely = 1.0 - y / window_size.height as f64;
+
+        let locked_sim = self.sim.lock().unwrap();
+        let m = locked_sim.model.ptr();
+        let d = locked_sim.state.ptr();
+        let mut selpnt = [0.0f64; 3];
+        let mut selgeom = 0i32;
+        let mut selflex = 0i32;
+        let mut selskin = 0i32;
+        let body = unsafe {
+            mjv_select(
+                m,
+                d,
+                &rendering.state.opt,
+                aspect,
+                relx,
+                rely,
+                &rendering.state.scn,
+                selpnt.as_mut_ptr(),
+                &mut selgeom,
+                &mut selflex,
+                &mut selskin,
+            )
+        };
+        if body >= 0 {
+            rendering.state.pert.select = body;
+            rendering.state.pert.active = mjtPertBit::TRANSLATE as i32;
+            unsafe {
+                // localpos is the grab point in the selected body's local
+                // frame, i.e. xmat^T * (selpnt - xpos); mjv_initPerturb only
+                // fills in refpos/refquat from the body's *current* pose, so
+                // without this the first drag snaps the body to the origin.
+                let body_idx = body as usize;
+                let xpos = std::slice::from_raw_parts((*d).xpos.add(3 * body_idx), 3);
+                let xmat = std::slice::from_raw_parts((*d).xmat.add(9 * body_idx), 9);
+                let rel = [
+                    selpnt[0] - xpos[0],
+                    selpnt[1] - xpos[1],
+                    selpnt[2] - xpos[2],
+                ];
+                for row in 0..3 {
+                    rendering.state.pert.localpos[row] =
+                        xmat[row] * rel[0] + xmat[3 + row] * rel[1] + xmat[6 + row] * rel[2];
+                }
+                mjv_initPerturb(m, d, &rendering.state.scn, &mut rendering.state.pert);
+            }
+        } else {
+            rendering.state.pert.select = 0;
+            rendering.state.pert.active = 0;
+        }
+    }
 }
 
 impl ApplicationHandler for MujocoApp {
@@ -268,6 +1006,18 @@ impl ApplicationHandler for MujocoApp {
         _window_id: glium::winit::window::WindowId,
         event: WindowEvent,
     ) {
+        if let Some(rendering) = self.rendering.as_mut() {
+            let response = rendering
+                .egui_winit_state
+                .on_window_event(&rendering.window, &event);
+            if response.repaint {
+                rendering.window.request_redraw();
+            }
+            if response.consumed {
+                return;
+            }
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
@@ -289,8 +1039,7 @@ impl ApplicationHandler for MujocoApp {
                 match locked_value.running_state {
                     PhysicsRunningState::Paused => {
                         if self.frame_rate_limited {
-                            locked_value.running_state =
-                                PhysicsRunningState::RateLimited;
+                            locked_value.running_state = PhysicsRunningState::RateLimited;
                         } else {
                             locked_value.running_state = PhysicsRunningState::Uncapped;
                         }
@@ -332,17 +1081,36 @@ impl ApplicationHandler for MujocoApp {
                     }
                 }
             }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.handle_mouse_input(state, button);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.handle_cursor_moved(position.x, position.y);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.handle_mouse_wheel(delta);
+            }
             _ => {}
         }
     }
 
-    fn about_to_wait(
-        &mut self,
-        _event_loop: &glium::winit::event_loop::ActiveEventLoop,
-    ) {
-        if let Some(rendering) = self.rendering.as_ref() {
+    fn about_to_wait(&mut self, event_loop: &glium::winit::event_loop::ActiveEventLoop) {
+        let Some(rendering) = self.rendering.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        if now >= rendering.next_redraw {
             rendering.window.request_redraw();
+            // Schedule from the target time, not `now`, so occasional slow
+            // frames don't permanently shift the cadence.
+            rendering.next_redraw += rendering.target_frame_interval;
+            if rendering.next_redraw < now {
+                rendering.next_redraw = now + rendering.target_frame_interval;
+            }
         }
+        event_loop.set_control_flow(glium::winit::event_loop::ControlFlow::WaitUntil(
+            rendering.next_redraw,
+        ));
     }
 }
 
@@ -350,6 +1118,16 @@ pub struct AppBuilder {
     ctrl_cb: Option<CtrlFun>,
     render_data: Option<Rendering>,
     model: mujoco_rust::Model,
+    xml_path: Option<String>,
+    offscreen_data: Option<OffscreenRendering>,
+    gamepad_mapping: Option<GamepadMappingSource>,
+}
+
+/// Defers resolving the default gamepad mapping until `build()`, since it
+/// needs the model's actuator count (`nu`), which isn't known beforehand.
+enum GamepadMappingSource {
+    Default,
+    Custom(GamepadMapping),
 }
 
 #[allow(dead_code)]
@@ -362,13 +1140,18 @@ impl AppBuilder {
             ctrl_cb: None,
             render_data: None,
             model,
+            xml_path: None,
+            offscreen_data: None,
+            gamepad_mapping: None,
         }
     }
 
     pub fn from_xml(xml: String) -> Result<Self, AppBuilderErr> {
         let model_result = mujoco_rust::Model::from_xml(xml.clone());
         if let Ok(model) = model_result {
-            Ok(AppBuilder::from_model(model))
+            let mut builder = AppBuilder::from_model(model);
+            builder.xml_path = Some(xml);
+            Ok(builder)
         } else {
             Err(AppBuilderErr(
                 format!("Failed to load xml file: '{}'", xml).to_string(),
@@ -378,6 +1161,7 @@ impl AppBuilder {
 
     pub fn build(mut self) -> MujocoApp {
         let sim = mujoco_rust::Simulation::new(self.model);
+        let nu = sim.model.nu();
 
         // If we're setting up rendering
         if let Some(rendering) = self.render_data.as_mut() {
@@ -392,9 +1176,28 @@ impl AppBuilder {
             }
         }
 
+        // If we're setting up offscreen rendering
+        if let Some(offscreen) = self.offscreen_data.as_mut() {
+            let state = &mut offscreen.state;
+            unsafe {
+                let m = sim.model.ptr();
+                mjv_makeScene(m, &mut state.scn, 1000);
+                mjr_makeContext(m, &mut state.con, 11);
+                mjr_setBuffer(mjtFramebuffer::OFFSCREEN as i32, &mut state.con);
+                mjv_defaultOption(&mut state.opt);
+                mjv_defaultFreeCamera(m, &mut state.cam);
+                mjv_defaultPerturb(&mut state.pert);
+            }
+        }
+
         // Propagate data by stepping simulation once
         sim.step();
 
+        let pending_reload = Arc::new(Mutex::new(false));
+        if let Some(xml_path) = self.xml_path.clone() {
+            spawn_xml_watcher(xml_path, pending_reload.clone());
+        }
+
         MujocoApp {
             ctrl_cb: self.ctrl_cb,
             rendering: self.render_data,
@@ -402,9 +1205,20 @@ impl AppBuilder {
             physics_state: Arc::new(Mutex::new(PhysicsState {
                 running_state: PhysicsRunningState::Paused,
                 frame_rate: 60.,
+                step_once: false,
+                reset_requested: false,
             })),
+            gui_ctrl: Arc::new(Mutex::new(None)),
             frame_rate_limited: true,
             last_render: Instant::now(),
+            xml_path: self.xml_path,
+            pending_reload,
+            reload_error: Arc::new(Mutex::new(None)),
+            offscreen: self.offscreen_data,
+            gamepad_mapping: self.gamepad_mapping.map(|source| match source {
+                GamepadMappingSource::Custom(mapping) => mapping,
+                GamepadMappingSource::Default => default_gamepad_mapping(nu),
+            }),
         }
     }
 
@@ -419,16 +1233,174 @@ impl AppBuilder {
             .expect("Failed to build event loop");
         let (window, display) = build_mujoco_gl_context(&event_loop);
 
+        let egui_ctx = egui::Context::default();
+        let egui_winit_state = egui_winit::State::new(
+            egui_ctx.clone(),
+            egui::ViewportId::ROOT,
+            &window,
+            Some(window.scale_factor() as f32),
+            None,
+            None,
+        );
+        let egui_painter =
+            egui_glium::Painter::new(&display, false, None).expect("Failed to create egui painter");
+
+        // Default to the monitor's own refresh rate so the viewer paces
+        // itself to the display instead of busy-rendering.
+        let refresh_rate_hz = window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .map(|mhz| mhz as f64 / 1000.0)
+            .unwrap_or(60.0);
+
         self.render_data = Some(Rendering {
             state: Default::default(),
             event_loop: Some(event_loop),
             window,
             display,
             render_cb: None,
+            egui_ctx,
+            egui_winit_state,
+            egui_painter,
+            mouse: MouseState::default(),
+            frame_buffers: FrameBuffers::default(),
+            target_frame_interval: Duration::from_secs_f64(1.0 / refresh_rate_hz),
+            next_redraw: Instant::now(),
         });
         self
     }
 
+    /// Overrides the default (monitor refresh rate) frame pacing target.
+    pub fn with_refresh_rate(mut self, fps: f64) -> Self {
+        if let Some(rendering) = self.render_data.as_mut() {
+            rendering.target_frame_interval = Duration::from_secs_f64(1.0 / fps);
+        }
+        self
+    }
+
+    /// Sets up headless offscreen rendering at a fixed resolution, for
+    /// dataset generation and trajectory videos on machines with no
+    /// display. Defaults to writing a numbered PNG sequence to the current
+    /// directory at 30 fps, capped to `DEFAULT_OFFSCREEN_DURATION_SECS`
+    /// seconds' worth of frames at that 30 fps so a forgotten cap can't fill
+    /// the disk (the cap is a frame count; `with_frame_rate` does not rescale
+    /// it);
+    /// override with `with_max_frames`/`with_duration`, or opt all the way
+    /// out with `without_frame_limit`. Override `with_frame_callback` to
+    /// receive decoded RGB frames directly instead of writing files.
+    pub fn with_offscreen_rendering(mut self, width: u32, height: u32) -> Self {
+        let event_loop = EventLoop::builder()
+            .build()
+            .expect("Failed to build event loop");
+        let (window, display) = build_offscreen_gl_context(&event_loop, width, height);
+        let fps = 30.0;
+
+        self.offscreen_data = Some(OffscreenRendering {
+            width,
+            height,
+            fps,
+            max_frames: Some(offscreen_frame_cap(DEFAULT_OFFSCREEN_DURATION_SECS, fps)),
+            state: Default::default(),
+            _window: window,
+            _display: display,
+            output: FrameOutput::Sequence {
+                dir: ".".to_string(),
+                prefix: "frame_".to_string(),
+                format: ImageFormat::Png,
+            },
+        });
+        self
+    }
+
+    pub fn with_frame_rate(mut self, fps: f64) -> Self {
+        if let Some(offscreen) = self.offscreen_data.as_mut() {
+            offscreen.fps = fps;
+        }
+        self
+    }
+
+    /// Caps `run_offscreen` to at most `frames` captured frames, so dataset
+    /// generation has a guaranteed end instead of running until killed.
+    pub fn with_max_frames(mut self, frames: u64) -> Self {
+        if let Some(offscreen) = self.offscreen_data.as_mut() {
+            offscreen.max_frames = Some(frames);
+        }
+        self
+    }
+
+    /// Caps `run_offscreen` to roughly `seconds` of simulated time, computed
+    /// from the current frame rate (set `with_frame_rate` first if
+    /// overriding the default).
+    pub fn with_duration(mut self, seconds: f64) -> Self {
+        if let Some(offscreen) = self.offscreen_data.as_mut() {
+            offscreen.max_frames = Some(offscreen_frame_cap(seconds, offscreen.fps));
+        }
+        self
+    }
+
+    /// Removes the default capture cap, letting `run_offscreen` capture
+    /// until the process is killed. Only meant for callers who terminate
+    /// the loop themselves (e.g. a `with_frame_callback` that tracks its own
+    /// stop condition).
+    pub fn without_frame_limit(mut self) -> Self {
+        if let Some(offscreen) = self.offscreen_data.as_mut() {
+            offscreen.max_frames = None;
+        }
+        self
+    }
+
+    pub fn with_frame_callback(mut self, callback: FrameFun) -> Result<Self, AppBuilderErr> {
+        if let Some(offscreen) = self.offscreen_data.as_mut() {
+            offscreen.output = FrameOutput::Callback(callback);
+            Ok(self)
+        } else {
+            Err(AppBuilderErr(
+                "'with_frame_callback' Requires offscreen rendering to be setup already!"
+                    .to_string(),
+            ))
+        }
+    }
+
+    pub fn with_frame_sequence(
+        mut self,
+        dir: String,
+        prefix: String,
+        format: ImageFormat,
+    ) -> Result<Self, AppBuilderErr> {
+        if let Some(offscreen) = self.offscreen_data.as_mut() {
+            offscreen.output = FrameOutput::Sequence {
+                dir,
+                prefix,
+                format,
+            };
+            Ok(self)
+        } else {
+            Err(AppBuilderErr(
+                "'with_frame_sequence' Requires offscreen rendering to be setup already!"
+                    .to_string(),
+            ))
+        }
+    }
+
+    /// Enables gamepad teleoperation using the default mapping: left/right
+    /// stick axes drive the first four actuators and the triggers the next
+    /// two, with deadzone filtering applied.
+    pub fn with_gamepad(mut self) -> Self {
+        self.gamepad_mapping = Some(GamepadMappingSource::Default);
+        self
+    }
+
+    /// Enables gamepad teleoperation with a custom mapping from
+    /// [`GamepadState`] to a control vector, overriding `ctrl_cb` whenever a
+    /// gamepad is connected.
+    pub fn with_gamepad_mapping(
+        mut self,
+        mapping: impl Fn(&GamepadState) -> Vec<f64> + Send + 'static,
+    ) -> Self {
+        self.gamepad_mapping = Some(GamepadMappingSource::Custom(Box::new(mapping)));
+        self
+    }
+
     pub fn with_custom_render_callback(
         mut self,
         render_function: RenderFun,
@@ -438,21 +1410,62 @@ impl AppBuilder {
             Ok(self)
         } else {
             Err(AppBuilderErr(
-                "'with_custom_render_callback' Requires rendering to be setup already!"
-                    .to_string(),
+                "'with_custom_render_callback' Requires rendering to be setup already!".to_string(),
             ))
         }
     }
 }
 
+/// Watches `xml_path` for changes on a background thread and flips
+/// `pending_reload` whenever the file settles after an edit, so the main
+/// thread can pick it up on the next redraw.
+fn spawn_xml_watcher(xml_path: String, pending_reload: Arc<Mutex<bool>>) {
+    thread::spawn(move || {
+        use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+
+        let (tx, rx) = mpsc::channel();
+        let mut debouncer = new_debouncer(Duration::from_millis(300), tx)
+            .expect("Failed to create XML file watcher");
+        debouncer
+            .watcher()
+            .watch(Path::new(&xml_path), RecursiveMode::NonRecursive)
+            .expect("Failed to watch XML file for changes");
+
+        // Keep the debouncer alive for the life of the thread.
+        for result in rx {
+            if result.is_ok() {
+                *pending_reload.lock().unwrap() = true;
+            }
+        }
+    });
+}
+
 // A default opengl window and context for mujoco
 fn build_mujoco_gl_context(
     event_loop: &impl GliumEventLoop,
 ) -> (glium::winit::window::Window, glium::Display<WindowSurface>) {
-    let window_attributes = Window::default_attributes();
+    build_gl_context(event_loop, Window::default_attributes())
+}
+
+// An opengl context backed by a hidden, fixed-size window, used to drive
+// offscreen rendering when there's no visible viewer.
+fn build_offscreen_gl_context(
+    event_loop: &impl GliumEventLoop,
+    width: u32,
+    height: u32,
+) -> (glium::winit::window::Window, glium::Display<WindowSurface>) {
+    let window_attributes = Window::default_attributes()
+        .with_visible(false)
+        .with_inner_size(glium::winit::dpi::PhysicalSize::new(width, height));
+    build_gl_context(event_loop, window_attributes)
+}
+
+fn build_gl_context(
+    event_loop: &impl GliumEventLoop,
+    window_attributes: glium::winit::window::WindowAttributes,
+) -> (glium::winit::window::Window, glium::Display<WindowSurface>) {
     let config_template_builder = ConfigTemplateBuilder::new();
-    let display_builder =
-        DisplayBuilder::new().with_window_attributes(Some(window_attributes));
+    let display_builder = DisplayBuilder::new().with_window_attributes(Some(window_attributes));
 
     let (window, gl_config) = event_loop
         .build(display_builder, config_template_builder, |mut configs| {
@@ -497,3 +1510,92 @@ fn build_mujoco_gl_context(
 
     (window, display)
 }
+
+#[cfg(test)]
+mod gamepad_tests {
+    use super::*;
+
+    #[test]
+    fn apply_deadzone_clamps_small_values_to_zero() {
+        assert_eq!(apply_deadzone(0.05, GAMEPAD_DEADZONE), 0.0);
+        assert_eq!(apply_deadzone(-0.05, GAMEPAD_DEADZONE), 0.0);
+    }
+
+    #[test]
+    fn apply_deadzone_passes_through_values_outside_the_deadzone() {
+        assert_eq!(apply_deadzone(0.5, GAMEPAD_DEADZONE), 0.5);
+        assert_eq!(apply_deadzone(-0.9, GAMEPAD_DEADZONE), -0.9f32 as f64);
+    }
+
+    #[test]
+    fn default_gamepad_mapping_matches_nu_and_maps_only_the_first_nu_axes() {
+        let mapping = default_gamepad_mapping(4);
+        let gamepad = GamepadState {
+            left_stick: [1.0, 2.0],
+            right_stick: [3.0, 4.0],
+            left_trigger: 5.0,
+            right_trigger: 6.0,
+        };
+        // nu < the number of gamepad axes: only the first nu axes are used.
+        assert_eq!(mapping(&gamepad), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn default_gamepad_mapping_zero_fills_actuators_beyond_the_gamepad_axes() {
+        let mapping = default_gamepad_mapping(8);
+        let gamepad = GamepadState {
+            left_stick: [1.0, 2.0],
+            right_stick: [3.0, 4.0],
+            left_trigger: 5.0,
+            right_trigger: 6.0,
+        };
+        assert_eq!(
+            mapping(&gamepad),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 0.0, 0.0]
+        );
+    }
+}
+
+#[cfg(test)]
+mod offscreen_tests {
+    use super::*;
+
+    #[test]
+    fn image_format_extension_matches_the_format() {
+        assert_eq!(ImageFormat::Png.extension(), "png");
+        assert_eq!(ImageFormat::Ppm.extension(), "ppm");
+    }
+
+    #[test]
+    fn write_frame_writes_a_valid_ppm_header_and_pixel_data() {
+        let rgb = vec![1u8, 2, 3, 4, 5, 6];
+        let path = std::env::temp_dir().join("mujoco_app_write_frame_test.ppm");
+        write_frame(path.to_str().unwrap(), &rgb, 1, 2, ImageFormat::Ppm);
+
+        let contents = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(contents, b"P6\n1 2\n255\n\x01\x02\x03\x04\x05\x06");
+    }
+
+    #[test]
+    fn due_for_next_frame_fires_once_sim_time_reaches_the_fps_boundary() {
+        let timestep = 0.01;
+        assert!(!due_for_next_frame(0.0, timestep, 1.0 / 30.0));
+        assert!(due_for_next_frame(1.0 / 30.0, timestep, 1.0 / 30.0));
+        // Within half a timestep of the boundary still counts, so capture
+        // timing doesn't depend on the step landing exactly on it.
+        assert!(due_for_next_frame(
+            1.0 / 30.0 - timestep / 2.0,
+            timestep,
+            1.0 / 30.0
+        ));
+    }
+
+    #[test]
+    fn offscreen_frame_cap_rounds_up_so_the_duration_is_never_cut_short() {
+        assert_eq!(offscreen_frame_cap(10.0, 30.0), 300);
+        // 1.0 / 3.0 seconds at 10 fps is 3.33 frames; round up to 4 rather
+        // than truncating the last partial frame away.
+        assert_eq!(offscreen_frame_cap(1.0 / 3.0, 10.0), 4);
+    }
+}